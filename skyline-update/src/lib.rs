@@ -2,12 +2,67 @@ use std::path::{PathBuf, Path};
 use std::io::prelude::*;
 use std::net::{TcpStream, IpAddr};
 
+use thiserror::Error;
+use miette::Diagnostic;
+
 use update_protocol::{Request, ResponseCode};
 
 pub use update_protocol::UpdateResponse;
 
 const PORT: u16 = 45000;
 
+/// Everything that can go wrong while checking for or installing an update.
+///
+/// Every `bool`-returning entry point in this crate has a `try_`-prefixed
+/// sibling (e.g. [`try_check_update`]) that returns this instead, so callers
+/// can branch on the specific failure. [`check_updates`] and [`uninstall`]
+/// return it directly.
+#[derive(Debug, Error, Diagnostic)]
+pub enum UpdateError {
+    #[error("failed to connect to the update server")]
+    Connect(#[source] std::io::Error),
+
+    #[error("failed to encode update request")]
+    Encode(#[source] serde_json::Error),
+
+    #[error("failed to decode update server response: {raw}")]
+    Decode { raw: String },
+
+    #[error("update server rejected the request: {0:?}")]
+    ServerRejected(ResponseCode),
+
+    #[error("plugin could not be found on the update server")]
+    PluginNotFound,
+
+    #[error("failed to download {}", path.display())]
+    Download {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("checksum mismatch for {}: expected {expected}, got {actual}", path.display())]
+    ChecksumMismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("failed to install {}", path.display())]
+    Install { path: PathBuf },
+}
+
+/// Outcome of a successful update check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateOutcome {
+    /// The plugin was already up to date.
+    NoUpdate,
+    /// An update was available, but the installer declined it.
+    Declined,
+    /// An update was available and was installed successfully.
+    Updated,
+}
+
 pub struct DefaultInstaller;
 
 #[cfg(not(target_os = "switch"))]
@@ -30,11 +85,6 @@ impl Installer for DefaultInstaller {
 #[cfg(target_os = "switch")]
 impl Installer for DefaultInstaller {
     fn should_update(&self, response: &UpdateResponse) -> bool {
-
-        if Path::new("sd:/installing.tmpfile").exists() {
-            return true;
-        }
-
         skyline_web::Dialog::yes_no(format!(
             "An update for {} has been found.\n\nWould you like to download it?",
             response.plugin_name
@@ -58,132 +108,351 @@ impl Installer for DefaultInstaller {
 pub trait Installer {
     fn should_update(&self, response: &UpdateResponse) -> bool;
     fn install_file(&self, path: PathBuf, buf: Vec<u8>) -> Result<(), ()>;
+
+    /// Called once before any files in the update are downloaded.
+    fn begin_transaction(&self) {}
+
+    /// Called once every required file has been staged and promoted to its
+    /// final location successfully. Safe to use to clear any backups kept
+    /// around for `rollback`.
+    fn commit(&self) {}
+
+    /// Called when an update fails partway through. By the time this runs,
+    /// `update()` has already restored any files it backed up, so this is
+    /// just a notification hook for installers that want to react to it.
+    fn rollback(&self) {}
+
+    /// Called after each chunk of `path` is downloaded, so a GUI can show a
+    /// real progress bar. `downloaded` and `total` are both byte counts.
+    fn on_progress(&self, _path: &Path, _downloaded: u64, _total: u64) {}
+
+    /// Removes a previously-installed file as part of [`uninstall`]. The
+    /// default implementation just deletes it off the filesystem directly.
+    fn remove_file(&self, path: &Path) -> Result<(), ()> {
+        std::fs::remove_file(path).map_err(|_| ())
+    }
 }
 
-fn update<I>(ip: IpAddr, response: &UpdateResponse, installer: &I) -> bool
+/// Chunk size used when streaming a file off the update socket.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Largest file `read_framed` will trust a declared length for before
+/// allocating a buffer for it. The server never packages anything close to
+/// this; anything bigger means a corrupted or malicious length header.
+const MAX_DOWNLOAD_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Reads one length-prefixed file off `stream`: an 8-byte big-endian total
+/// length, followed by that many bytes. Reads in `CHUNK_SIZE` pieces,
+/// reporting progress after each one, instead of buffering the whole
+/// response in a single `read_to_end`.
+fn read_framed<I>(stream: &mut TcpStream, path: &Path, installer: &I) -> std::io::Result<Vec<u8>>
     where I: Installer,
 {
+    let mut len_buf = [0u8; 8];
+    stream.read_exact(&mut len_buf)?;
+    let total = u64::from_be_bytes(len_buf);
 
-    /* Remove dir(s) before installing. This makes sure that even if you remove files in your folders it will update properly */
-    if !Path::new("sd:/installing.tmpfile").exists() {
-        for file in &response.required_files {
-            if let update_protocol::InstallLocation::AbsolutePath(p) = &file.install_location {
-                let p = Path::new(&p);
-                if p.is_dir() && p.exists() {
-                    println!("Deleting folder before update: {:#?}", p);
-                    let _ = std::fs::remove_dir_all(p);
-                }
+    if total > MAX_DOWNLOAD_SIZE {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("declared download size {} exceeds the {} byte limit", total, MAX_DOWNLOAD_SIZE),
+        ));
+    }
+
+    let mut buf = Vec::with_capacity(total as usize);
+    let mut chunk = [0u8; CHUNK_SIZE];
+    let mut downloaded = 0u64;
+
+    while downloaded < total {
+        let remaining = (total - downloaded).min(CHUNK_SIZE as u64) as usize;
+        stream.read_exact(&mut chunk[..remaining])?;
+        buf.extend_from_slice(&chunk[..remaining]);
+        downloaded += remaining as u64;
+        installer.on_progress(path, downloaded, total);
+    }
+
+    Ok(buf)
+}
+
+const STAGING_DIR: &str = "sd:/.update_staging";
+const BACKUP_DIR: &str = "sd:/.update_backup";
+const MANIFEST_DIR: &str = "sd:/.skyline-update";
+
+fn manifest_path(plugin_name: &str) -> PathBuf {
+    Path::new(MANIFEST_DIR).join(format!("{}.installed.json", plugin_name))
+}
+
+/// Records the paths `update()` just installed for `plugin_name`.
+fn write_manifest(plugin_name: &str, paths: &[String]) {
+    let _ = std::fs::create_dir_all(MANIFEST_DIR);
+    if let Ok(json) = serde_json::to_string(paths) {
+        let _ = std::fs::write(manifest_path(plugin_name), json);
+    }
+}
+
+fn read_manifest(plugin_name: &str) -> Option<Vec<String>> {
+    let contents = std::fs::read_to_string(manifest_path(plugin_name)).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Strips the `sd:/` prefix so an absolute path can be reused as a relative
+/// key under the staging/backup directories.
+fn relative_key(path: &Path) -> PathBuf {
+    path.strip_prefix("sd:/").unwrap_or(path).to_path_buf()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hex::encode(hasher.finalize())
+}
+
+/// Undoes a partially-applied update: restores `backed_up` from
+/// `backup_root`, removes `created` since there's nothing to restore those to.
+fn rollback_commit(backup_root: &Path, backed_up: &[PathBuf], created: &[PathBuf]) {
+    for path in backed_up {
+        let backup_path = backup_root.join(relative_key(path));
+        let _ = std::fs::rename(&backup_path, path);
+    }
+    for path in created {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+fn try_update<I>(ip: IpAddr, response: &UpdateResponse, installer: &I) -> Result<(), UpdateError>
+    where I: Installer,
+{
+    let plugin_name = &response.plugin_name;
+    let staging_root = Path::new(STAGING_DIR).join(plugin_name);
+    let backup_root = Path::new(BACKUP_DIR).join(plugin_name);
+
+    installer.begin_transaction();
+
+    /* Stage every file under sd:/.update_staging instead of writing over the
+     * real install location until everything has downloaded. All files are
+     * pulled over a single connection (one download_index request per file,
+     * each answered with a length-prefixed, chunked payload). */
+    let mut stream = match TcpStream::connect_timeout(&std::net::SocketAddr::new(ip, PORT + 1), std::time::Duration::new(10, 0)) {
+        Ok(stream) => stream,
+        Err(e) => {
+            installer.rollback();
+            return Err(UpdateError::Connect(e))
+        }
+    };
+
+    for file in &response.required_files {
+
+        let path: PathBuf = match &file.install_location {
+            update_protocol::InstallLocation::AbsolutePath(path) => path.into(),
+            _ => {
+                let _ = std::fs::remove_dir_all(&staging_root);
+                installer.rollback();
+                return Err(UpdateError::Decode { raw: format!("{:?}", file.install_location) })
             }
+        };
+
+        let staged_path = staging_root.join(relative_key(&path));
+
+        /* An earlier attempt that dropped mid-batch may already have staged
+         * this file; reuse it if it still matches the expected checksum. */
+        if let Some(expected) = &file.sha256 {
+            if std::fs::read(&staged_path).map(|data| sha256_hex(&data) == *expected).unwrap_or(false) {
+                continue;
+            }
+        }
+
+        let _ = stream.write_all(&u64::to_be_bytes(file.download_index));
+        let buf = match read_framed(&mut stream, &path, installer) {
+            Ok(buf) => buf,
+            Err(e) => {
+                /* Leave the staging dir in place: whatever already downloaded
+                 * successfully can be resumed from on the next attempt. */
+                installer.rollback();
+                return Err(UpdateError::Download { path, source: e })
+            }
+        };
+
+        if let Some(expected) = &file.sha256 {
+            let actual = sha256_hex(&buf);
+            if &actual != expected {
+                installer.rollback();
+                return Err(UpdateError::ChecksumMismatch { path, expected: expected.clone(), actual })
+            }
+        }
+
+        println!("Downloaded {:#?}", path.clone());
+
+        if installer.install_file(staged_path, buf).is_err() {
+            let _ = std::fs::remove_dir_all(&staging_root);
+            installer.rollback();
+            return Err(UpdateError::Install { path })
         }
     }
 
-    for file in &response.required_files {
+    let _ = stream.flush();
+    let _ = stream.shutdown(std::net::Shutdown::Both);
 
+    /* Every file staged successfully, so back up what's at each install
+     * location and move the staged files into place. If this fails partway,
+     * roll back what's been done so far instead of leaving a half-applied
+     * update. */
+    let mut backed_up = Vec::new();
+    let mut created = Vec::new();
+    for file in &response.required_files {
         let path: PathBuf = match &file.install_location {
             update_protocol::InstallLocation::AbsolutePath(path) => path.into(),
-            _ => return false
+            _ => continue,
         };
 
-        if path.exists() && Path::new("sd:/installing.tmpfile").exists() && path.extension().unwrap_or_default() != "nro" {
+        let staged_path = staging_root.join(relative_key(&path));
+        if !staged_path.exists() {
             continue;
         }
-        match TcpStream::connect_timeout(&std::net::SocketAddr::new(ip, PORT + 1), std::time::Duration::new(10, 0)) { 
-            Ok(mut stream) => {
-                let mut buf = vec![];
-                let _ = stream.write_all(&u64::to_be_bytes(file.download_index));
-                if let Err(e) = stream.read_to_end(&mut buf) {
-                    println!("[updater] Error downloading file: {}", e);
-                    return false
-                }
 
-                println!("Downloaded {:#?}", path.clone());
-    
-                if installer.install_file(path, buf).is_err() {
-                    return false
-                }
-                let _ = stream.flush();
-                let _ = stream.shutdown(std::net::Shutdown::Both);
+        let existed = path.exists();
+        if existed {
+            let backup_path = backup_root.join(relative_key(&path));
+            if let Some(parent) = backup_path.parent() {
+                let _ = std::fs::create_dir_all(parent);
             }
-            Err(e) => {
-                println!("[updater] Failed to connect to port {}", PORT + 1);
-                println!("Err: {}", e);
-                /* Hacky solution to descriptor table filling up */
-                if e.to_string().contains("os error 24") {
-                    println!("Recovering download...");
-                    std::fs::File::create(Path::new("sd:/installing.tmpfile")).unwrap();
-                    skyline::nn::oe::RestartProgramNoArgs();
-                }
-                return false
+            if std::fs::rename(&path, &backup_path).is_err() {
+                rollback_commit(&backup_root, &backed_up, &created);
+                let _ = std::fs::remove_dir_all(&staging_root);
+                let _ = std::fs::remove_dir_all(&backup_root);
+                installer.rollback();
+                return Err(UpdateError::Install { path })
             }
-        };
+            backed_up.push(path.clone());
+        }
+
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if std::fs::rename(&staged_path, &path).is_err() {
+            rollback_commit(&backup_root, &backed_up, &created);
+            let _ = std::fs::remove_dir_all(&staging_root);
+            let _ = std::fs::remove_dir_all(&backup_root);
+            installer.rollback();
+            return Err(UpdateError::Install { path })
+        }
+
+        if !existed {
+            created.push(path);
+        }
     }
+
+    let installed_paths: Vec<String> = response.required_files.iter()
+        .filter_map(|file| match &file.install_location {
+            update_protocol::InstallLocation::AbsolutePath(p) => Some(p.clone()),
+            _ => None,
+        })
+        .collect();
+
+    /* A new version may drop files the previous one installed. Diff against
+     * the manifest from the last install and back up (so it's still
+     * rollback-able) and remove whatever's no longer listed. */
+    if let Some(previous_paths) = read_manifest(plugin_name) {
+        for old_path in previous_paths {
+            if installed_paths.iter().any(|p| *p == old_path) {
+                continue;
+            }
+
+            let old_path = PathBuf::from(old_path);
+            if !old_path.exists() {
+                continue;
+            }
+
+            let backup_path = backup_root.join(relative_key(&old_path));
+            if let Some(parent) = backup_path.parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+            if std::fs::rename(&old_path, &backup_path).is_err() {
+                rollback_commit(&backup_root, &backed_up, &created);
+                let _ = std::fs::remove_dir_all(&staging_root);
+                let _ = std::fs::remove_dir_all(&backup_root);
+                installer.rollback();
+                return Err(UpdateError::Install { path: old_path })
+            }
+            backed_up.push(old_path);
+        }
+    }
+
+    let _ = std::fs::remove_dir_all(&staging_root);
+    let _ = std::fs::remove_dir_all(&backup_root);
+    installer.commit();
+
+    write_manifest(plugin_name, &installed_paths);
+
     println!("[updater] finished updating plugin.");
-    let _ = std::fs::remove_file("sd:/installing.tmpfile");
-    true
+    Ok(())
 }
 
-/// Install an update with a custom installer implementation
-pub fn custom_check_update<I>(ip: IpAddr, name: &str, version: &str, allow_beta: bool, installer: &I) -> bool
+/// Check for, and optionally install, an update with a custom installer
+/// implementation. See [`custom_check_update`] for a `bool`-returning
+/// equivalent kept for source compatibility.
+pub fn try_custom_check_update<I>(ip: IpAddr, name: &str, version: &str, allow_beta: bool, installer: &I) -> Result<UpdateOutcome, UpdateError>
     where I: Installer,
 {
-    match TcpStream::connect_timeout(&std::net::SocketAddr::new(ip, PORT), std::time::Duration::new(10, 0)) {
-        Ok(mut stream) =>  {
-            if let Ok(packet) = serde_json::to_string(&Request::Update {
-                beta: Some(allow_beta),
-                plugin_name: name.to_owned(),
-                plugin_version: version.to_owned(),
-                options: None,
-            }) {
-                let _ = stream.write_fmt(format_args!("{}\n", packet));
-                let mut string = String::new();
-                let _ = stream.read_to_string(&mut string);
-
-                if let Ok(response) = serde_json::from_str::<UpdateResponse>(&string) {
-                    match response.code {
-                        ResponseCode::NoUpdate => return false,
-                        ResponseCode::Update => {
-                            if installer.should_update(&response) {
-                                let success = update(ip, &response, installer);
-
-                                if !success {
-                                    println!("[{} updater] Failed to install update, files may be left in a broken state.", name);
-                                }
-
-                                success
-                            } else {
-                                false
-                            }
-                        }
-                        ResponseCode::InvalidRequest => {
-                            println!("[{} updater] Failed to send a valid request to the server", name);
-                            false
-                        }
-                        ResponseCode::PluginNotFound => {
-                            println!("Plugin '{}' could not be found on the update server", name);
-                            false
-                        }
-                        _ => {
-                            println!("Unexpected response");
-                            false
-                        }
-                    }
-                } else {
-                    println!("[{} updater] Failed to parse update server response: {:?}", name, string);
-                    false
-                }
+    let mut stream = TcpStream::connect_timeout(&std::net::SocketAddr::new(ip, PORT), std::time::Duration::new(10, 0))
+        .map_err(UpdateError::Connect)?;
+
+    let packet = serde_json::to_string(&Request::Update {
+        beta: Some(allow_beta),
+        plugin_name: name.to_owned(),
+        plugin_version: version.to_owned(),
+        options: None,
+    }).map_err(UpdateError::Encode)?;
+
+    let _ = stream.write_fmt(format_args!("{}\n", packet));
+    let mut string = String::new();
+    let _ = stream.read_to_string(&mut string);
+
+    let response: UpdateResponse = serde_json::from_str(&string)
+        .map_err(|_| UpdateError::Decode { raw: string })?;
+
+    match &response.code {
+        ResponseCode::NoUpdate => Ok(UpdateOutcome::NoUpdate),
+        ResponseCode::Update => {
+            if installer.should_update(&response) {
+                try_update(ip, &response, installer)?;
+                Ok(UpdateOutcome::Updated)
             } else {
-                println!("[{} updater] Failed to encode packet", name);
-                false
+                Ok(UpdateOutcome::Declined)
             }
         }
+        ResponseCode::PluginNotFound => Err(UpdateError::PluginNotFound),
+        other => Err(UpdateError::ServerRejected(other.clone())),
+    }
+}
+
+/// Install an update with a custom installer implementation
+pub fn custom_check_update<I>(ip: IpAddr, name: &str, version: &str, allow_beta: bool, installer: &I) -> bool
+    where I: Installer,
+{
+    match try_custom_check_update(ip, name, version, allow_beta, installer) {
+        Ok(UpdateOutcome::Updated) => true,
+        Ok(_) => false,
         Err(e) => {
-            println!("[{} updater] Failed to connect to update server {}", name, ip);
-            println!("[{} updater] {:?}", name, e);
+            println!("[{} updater] {}", name, e);
             false
         }
     }
 }
 
+/// Check for an update using the default installer. See [`check_update`]
+/// for a `bool`-returning equivalent kept for source compatibility.
+///
+/// ## Args
+/// * ip - IP address of server
+/// * name - name of plugin to update
+/// * version - current version of plugin
+/// * allow_beta - allow beta versions to be offered
+pub fn try_check_update(ip: IpAddr, name: &str, version: &str, allow_beta: bool) -> Result<UpdateOutcome, UpdateError> {
+    try_custom_check_update(ip, name, version, allow_beta, &DefaultInstaller)
+}
+
 /// Install an update using the default installer
 ///
 /// ## Args
@@ -192,43 +461,146 @@ pub fn custom_check_update<I>(ip: IpAddr, name: &str, version: &str, allow_beta:
 /// * version - current version of plugin
 /// * allow_beta - allow beta versions to be offered
 pub fn check_update(ip: IpAddr, name: &str, version: &str, allow_beta: bool) -> bool {
-    custom_check_update(ip, name, version, allow_beta, &DefaultInstaller)
+    matches!(try_check_update(ip, name, version, allow_beta), Ok(UpdateOutcome::Updated))
+}
+
+pub fn try_get_update_info(ip: IpAddr, name: &str, version: &str, allow_beta: bool) -> Result<UpdateResponse, UpdateError> {
+    let mut stream = TcpStream::connect_timeout(&std::net::SocketAddr::new(ip, PORT), std::time::Duration::new(10, 0))
+        .map_err(UpdateError::Connect)?;
+
+    let packet = serde_json::to_string(&Request::Update {
+        beta: Some(allow_beta),
+        plugin_name: name.to_owned(),
+        plugin_version: version.to_owned(),
+        options: None,
+    }).map_err(UpdateError::Encode)?;
+
+    let _ = stream.write_fmt(format_args!("{}\n", packet));
+    let mut string = String::new();
+    let _ = stream.read_to_string(&mut string);
+
+    serde_json::from_str(&string).map_err(|_| UpdateError::Decode { raw: string })
 }
 
 pub fn get_update_info(ip: IpAddr, name: &str, version: &str, allow_beta: bool) -> Option<UpdateResponse> {
-    match TcpStream::connect_timeout(&std::net::SocketAddr::new(ip, PORT), std::time::Duration::new(10, 0)) {
-        Ok(mut stream) =>  {
-            if let Ok(packet) = serde_json::to_string(&Request::Update {
-                beta: Some(allow_beta),
-                plugin_name: name.to_owned(),
-                plugin_version: version.to_owned(),
-                options: None,
-            }) {
-                let _ = stream.write_fmt(format_args!("{}\n", packet));
-                let mut string = String::new();
-                let _ = stream.read_to_string(&mut string);
-
-                if let Ok(response) = serde_json::from_str::<UpdateResponse>(&string) {
-                    Some(response)
+    try_get_update_info(ip, name, version, allow_beta).ok()
+}
+
+pub fn try_install_update(ip: IpAddr, info: &UpdateResponse) -> Result<(), UpdateError> {
+    try_update(ip, info, &DefaultInstaller)
+}
+
+pub fn install_update(ip: IpAddr, info: &UpdateResponse) -> bool {
+    try_install_update(ip, info).is_ok()
+}
+
+/// Check for updates to several plugins in one session, rather than opening
+/// a new connection and sending a separate `Request::Update` per plugin.
+///
+/// `plugins` is a list of `(name, version, allow_beta)` to query for. Every
+/// plugin with an available update that `installer` accepts is installed as
+/// part of the same batch. The returned `Vec` carries each plugin's
+/// `UpdateResponse` alongside the outcome of installing it, in request
+/// order; one plugin failing to install doesn't abort the rest of the batch.
+pub fn check_updates<I>(ip: IpAddr, plugins: &[(&str, &str, bool)], installer: &I) -> Result<Vec<(String, UpdateResponse, Result<UpdateOutcome, UpdateError>)>, UpdateError>
+    where I: Installer,
+{
+    let mut stream = TcpStream::connect_timeout(&std::net::SocketAddr::new(ip, PORT), std::time::Duration::new(10, 0))
+        .map_err(UpdateError::Connect)?;
+
+    let packet = serde_json::to_string(&Request::UpdateList {
+        plugins: plugins.iter().map(|(name, version, beta)| update_protocol::PluginQuery {
+            plugin_name: (*name).to_owned(),
+            plugin_version: (*version).to_owned(),
+            beta: Some(*beta),
+        }).collect(),
+    }).map_err(UpdateError::Encode)?;
+
+    let _ = stream.write_fmt(format_args!("{}\n", packet));
+    let mut string = String::new();
+    let _ = stream.read_to_string(&mut string);
+
+    let responses: Vec<UpdateResponse> = serde_json::from_str(&string)
+        .map_err(|_| UpdateError::Decode { raw: string })?;
+
+    let mut results = Vec::with_capacity(responses.len());
+    for response in responses {
+        let outcome = match &response.code {
+            ResponseCode::NoUpdate => Ok(UpdateOutcome::NoUpdate),
+            ResponseCode::Update => {
+                if installer.should_update(&response) {
+                    try_update(ip, &response, installer).map(|()| UpdateOutcome::Updated)
                 } else {
-                    None
+                    Ok(UpdateOutcome::Declined)
                 }
-            } else {
-                None
             }
-        }
-        Err(_) => None,
+            ResponseCode::PluginNotFound => Err(UpdateError::PluginNotFound),
+            other => Err(UpdateError::ServerRejected(other.clone())),
+        };
+        results.push((response.plugin_name.clone(), response, outcome));
     }
+
+    Ok(results)
 }
 
-pub fn install_update(ip: IpAddr, info: &UpdateResponse) -> bool {
-    update(ip, info, &DefaultInstaller)
+/// Asks the update server for the install paths of the version of `name` it
+/// currently hosts, falling back to the local record at
+/// `sd:/.skyline-update/<name>.installed.json` if the server can't be
+/// reached.
+fn remove_manifest(ip: IpAddr, name: &str, version: &str) -> Result<Vec<String>, UpdateError> {
+    let remote = (|| -> Result<Vec<String>, UpdateError> {
+        let mut stream = TcpStream::connect_timeout(&std::net::SocketAddr::new(ip, PORT), std::time::Duration::new(10, 0))
+            .map_err(UpdateError::Connect)?;
+
+        let packet = serde_json::to_string(&Request::Remove {
+            plugin_name: name.to_owned(),
+            plugin_version: version.to_owned(),
+        }).map_err(UpdateError::Encode)?;
+
+        let _ = stream.write_fmt(format_args!("{}\n", packet));
+        let mut string = String::new();
+        let _ = stream.read_to_string(&mut string);
+
+        serde_json::from_str(&string).map_err(|_| UpdateError::Decode { raw: string })
+    })();
+
+    match remote {
+        Ok(paths) => Ok(paths),
+        Err(e) => read_manifest(name).ok_or(e),
+    }
+}
+
+/// Removes the files listed in a manifest for plugin `name`, instead of
+/// deleting the whole install folder and hoping nothing unrelated ended up
+/// in it. The manifest comes from the update server, or from the local
+/// record left behind by `update()` if the server is unreachable.
+pub fn uninstall<I>(ip: IpAddr, name: &str, version: &str, installer: &I) -> Result<(), UpdateError>
+    where I: Installer,
+{
+    let manifest = remove_manifest(ip, name, version)?;
+
+    for path in &manifest {
+        let _ = installer.remove_file(Path::new(path));
+    }
+
+    let _ = std::fs::remove_file(manifest_path(name));
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b"hello world"),
+            "b94d27b9934d3e08a52e52d7da7dacefbd40d4b3e75a786d2f68f94f3f2e3f28"
+        );
+        assert_ne!(sha256_hex(b"hello world"), sha256_hex(b"goodbye world"));
+    }
+
     #[test]
     fn test_install() {
         println!("{}", serde_json::to_string(&Request::Update { plugin_name: "test_name".into(), plugin_version: "1.0.0".into(), beta: None, options: None }).unwrap());
@@ -5,6 +5,31 @@ use update_protocol::InstallLocation;
 use serde::{Serialize, Deserialize};
 
 use color_eyre::eyre;
+use thiserror::Error;
+use miette::{Diagnostic, NamedSource, SourceSpan};
+
+/// Problems with a single plugin's `plugin.toml` (or the folder it lives
+/// in) that stop it from being packaged. `folder_to_plugin` returns these
+/// instead of panicking, so one malformed plugin folder doesn't take the
+/// whole server down with it.
+#[derive(Debug, Error, Diagnostic)]
+pub enum PluginTomlError {
+    #[error("failed to parse plugin.toml: {message}")]
+    #[diagnostic()]
+    Parse {
+        #[source_code]
+        src: NamedSource<String>,
+        #[label("{message}")]
+        span: SourceSpan,
+        message: String,
+    },
+
+    #[error("path `{}` is not valid UTF-8", path.display())]
+    NonUtf8Path { path: PathBuf },
+
+    #[error("folder `{}` is not nested under a `plugins` directory", path.display())]
+    FolderNotUnderPlugins { path: PathBuf },
+}
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct PluginFile {
@@ -104,23 +129,42 @@ pub struct Metadata {
     pub changelog: Option<String>,
 }
 
+/// A file ready to be served to clients, along with the checksum they
+/// should use to verify the download before installing it.
+pub struct PackagedFile {
+    pub install_location: InstallLocation,
+    pub data: Vec<u8>,
+    pub sha256: Option<String>,
+}
+
 pub struct Plugin {
     pub name: String,
     pub plugin_version: Version,
-    pub files: Vec<(InstallLocation, Vec<u8>)>,
+    pub files: Vec<PackagedFile>,
     pub skyline_version: Version,
     pub beta: bool,
     pub metadata: Metadata,
 }
 
-fn to_file(PluginFile { install_location, filename }: PluginFile, dir: &Path) -> eyre::Result<(InstallLocation, Vec<u8>)> {
+fn sha256_hex(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hex::encode(hasher.finalize())
+}
+
+fn to_file(PluginFile { install_location, filename }: PluginFile, dir: &Path) -> eyre::Result<PackagedFile> {
     let path = if filename.is_absolute() {
         filename
     } else {
         dir.join(filename)
     };
 
-    Ok((install_location, fs::read(path)?))
+    let data = fs::read(path)?;
+    let sha256 = Some(sha256_hex(&data));
+
+    Ok(PackagedFile { install_location, data, sha256 })
 }
 
 pub fn folder_to_plugin(dir: io::Result<fs::DirEntry>) -> eyre::Result<Option<Plugin>> {
@@ -130,21 +174,32 @@ pub fn folder_to_plugin(dir: io::Result<fs::DirEntry>) -> eyre::Result<Option<Pl
     }
     let toml_path = path.join("plugin.toml");
 
-    let plugin: PluginToml = toml::from_str(&fs::read_to_string(toml_path)?)?;
+    let raw_toml = fs::read_to_string(&toml_path)?;
+    let plugin: PluginToml = toml::from_str(&raw_toml).map_err(|e| {
+        let span = e.span().map(|r| (r.start, r.end - r.start).into()).unwrap_or_else(|| (0, 0).into());
+        PluginTomlError::Parse {
+            src: NamedSource::new(toml_path.display().to_string(), raw_toml.clone()),
+            span,
+            message: e.message().to_string(),
+        }
+    })?;
 
     let PluginToml { version, name, files, folders, skyline_version, beta, metadata } =  plugin;
 
-    let mut files: Vec<(InstallLocation, Vec<u8>)> = files.into_iter().map(|file| to_file(file, &path)).collect::<eyre::Result<_>>()?;
+    let mut files: Vec<PackagedFile> = files.into_iter().map(|file| to_file(file, &path)).collect::<eyre::Result<_>>()?;
 
     /* Handle directories */
     for folder in folders.unwrap_or_default() {
 
+        let root_name = folder.root_name.to_str()
+            .ok_or_else(|| PluginTomlError::NonUtf8Path { path: folder.root_name.clone() })?;
+
         /* cwd joined with our current "plugin" joined with our current folder. */
-        let root_path = &std::env::current_dir().unwrap().join(path.join(Path::new(folder.root_name.to_str().unwrap())));
+        let root_path = &std::env::current_dir()?.join(path.join(Path::new(root_name)));
 
         /* recurse through folder and push each file onto files vector. */
         for file_from_folder in walkdir::WalkDir::new(root_path).contents_first(true) {
-            let file_from_folder = file_from_folder.unwrap();
+            let file_from_folder = file_from_folder?;
             if file_from_folder.path().is_dir() {
                 continue;
             }
@@ -153,16 +208,37 @@ pub fn folder_to_plugin(dir: io::Result<fs::DirEntry>) -> eyre::Result<Option<Pl
                 _ => Path::new("ERR")
             };
 
-            let mut file_from_folder_path: Vec<&str> = file_from_folder.path().to_str().unwrap().split("/").collect();
-            let append_idx = file_from_folder_path.clone().into_iter().position(|x| x == "plugins").unwrap() + 3;
+            let file_from_folder_path_str = file_from_folder.path().to_str()
+                .ok_or_else(|| PluginTomlError::NonUtf8Path { path: file_from_folder.path().to_path_buf() })?;
+            let mut file_from_folder_path: Vec<&str> = file_from_folder_path_str.split("/").collect();
+
+            /* The path under `root_path` we actually want to install is
+             * whatever comes after `.../plugins/<plugin>/<folder root>/`, so
+             * find the `plugins` segment and step over the plugin name and
+             * folder root name to get to it. */
+            let plugins_idx = file_from_folder_path.iter().position(|x| *x == "plugins")
+                .ok_or_else(|| PluginTomlError::FolderNotUnderPlugins { path: file_from_folder.path().to_path_buf() })?;
+            let append_idx = plugins_idx + 3;
+            if append_idx > file_from_folder_path.len() {
+                return Err(PluginTomlError::FolderNotUnderPlugins { path: file_from_folder.path().to_path_buf() }.into());
+            }
             let append_path = file_from_folder_path.split_off(append_idx).join("/");
             let install_path = install_loc.join(&append_path);
 
-            let file_data = ( InstallLocation::AbsolutePath(install_path.to_str().unwrap().to_string()), fs::read(file_from_folder.path())? );
+            let install_path_str = install_path.to_str()
+                .ok_or_else(|| PluginTomlError::NonUtf8Path { path: install_path.clone() })?
+                .to_string();
+
+            let data = fs::read(file_from_folder.path())?;
+            let file_data = PackagedFile {
+                install_location: InstallLocation::AbsolutePath(install_path_str),
+                sha256: Some(sha256_hex(&data)),
+                data,
+            };
 
             files.insert(0, file_data)
         }
-        files.push( ( folder.install_root_location, vec![] ) );
+        files.push( PackagedFile { install_location: folder.install_root_location, data: vec![], sha256: None } );
 
     }
     
@@ -192,7 +268,13 @@ pub fn get() -> eyre::Result<Vec<Plugin>> {
                 match folder_to_plugin(entry) {
                     Ok(x) => x,
                     Err(e) => {
-                        println!("{}", e);
+                        /* Render through miette so a `PluginTomlError::Parse`
+                         * prints its `#[source_code]`/`#[label]` as a
+                         * pinpointed diagnostic instead of just the message. */
+                        match e.downcast::<PluginTomlError>() {
+                            Ok(e) => println!("{:?}", miette::Report::new(e)),
+                            Err(e) => println!("{}", e),
+                        }
                         None
                     }
                 }
@@ -201,6 +283,57 @@ pub fn get() -> eyre::Result<Vec<Plugin>> {
     )
 }
 
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dir_entry_for(dir: &Path) -> io::Result<fs::DirEntry> {
+        fs::read_dir(dir.parent().unwrap())?
+            .find(|entry| entry.as_ref().map(|e| e.path() == dir).unwrap_or(false))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_error_has_span() {
+        let dir = std::env::temp_dir().join("hosted_plugins_test_parse_error");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("plugin.toml"), "this is not valid toml {{{").unwrap();
+
+        assert!(matches!(
+            folder_to_plugin(dir_entry_for(&dir)).unwrap_err().downcast::<PluginTomlError>(),
+            Ok(PluginTomlError::Parse { span, .. }) if span.offset() > 0
+        ));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_folder_not_under_plugins() {
+        let dir = std::env::temp_dir().join("hosted_plugins_test_folder_not_under_plugins");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("assets")).unwrap();
+        fs::write(dir.join("assets").join("file.txt"), b"data").unwrap();
+        fs::write(dir.join("plugin.toml"), r#"
+            version = "1.0.0"
+            name = "test"
+            files = []
+
+            [[folders]]
+            root_name = "assets"
+            install_root_location = { AbsolutePath = "sd:/ultimate/mods/test" }
+        "#).unwrap();
+
+        let err = folder_to_plugin(dir_entry_for(&dir)).unwrap_err();
+        assert!(matches!(
+            err.downcast::<PluginTomlError>(),
+            Ok(PluginTomlError::FolderNotUnderPlugins { .. })
+        ));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
+
 /*pub fn print_default() {
     println!("{}", toml::to_string_pretty(&PluginToml {
         name: "name".to_owned(),
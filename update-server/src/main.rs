@@ -0,0 +1,214 @@
+mod hosted_plugins;
+
+use std::collections::HashMap;
+use std::io::prelude::*;
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+
+use color_eyre::eyre;
+use semver::Version;
+use update_protocol::{Request, RequiredFile, ResponseCode, UpdateResponse};
+
+const PORT: u16 = 45000;
+
+/// A hosted plugin's update-relevant state, with every file's bytes already
+/// read off disk at startup and its `download_index` already pointing into
+/// [`Catalog::files`].
+struct PluginEntry {
+    version: Version,
+    beta: bool,
+    required_files: Vec<RequiredFile>,
+}
+
+/// Everything the server knows about the plugins it's hosting, loaded once
+/// from `plugins/` at startup.
+///
+/// `files` is flat across every plugin rather than per-plugin: the `PORT +
+/// 1` download connection only ever receives a bare `download_index` with
+/// no other context, so indices have to be globally unique to be resolved.
+struct Catalog {
+    plugins: HashMap<String, PluginEntry>,
+    files: Vec<Vec<u8>>,
+}
+
+impl PluginEntry {
+    fn installed_paths(&self) -> Vec<String> {
+        self.required_files.iter()
+            .filter_map(|file| match &file.install_location {
+                update_protocol::InstallLocation::AbsolutePath(p) => Some(p.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Catalog {
+    fn load() -> eyre::Result<Self> {
+        let mut plugins = HashMap::new();
+        let mut files = Vec::new();
+
+        for plugin in hosted_plugins::get()? {
+            let required_files = plugin.files.into_iter().map(|file| {
+                let download_index = files.len() as u64;
+                files.push(file.data);
+
+                RequiredFile {
+                    install_location: file.install_location,
+                    download_index,
+                    sha256: file.sha256,
+                }
+            }).collect();
+
+            plugins.insert(plugin.name, PluginEntry {
+                version: plugin.plugin_version,
+                beta: plugin.beta,
+                required_files,
+            });
+        }
+
+        Ok(Catalog { plugins, files })
+    }
+
+    fn response_for(&self, plugin_name: &str, plugin_version: &str, allow_beta: bool) -> UpdateResponse {
+        let Some(entry) = self.plugins.get(plugin_name) else {
+            return UpdateResponse {
+                code: ResponseCode::PluginNotFound,
+                plugin_name: plugin_name.to_owned(),
+                required_files: vec![],
+            };
+        };
+
+        let Ok(client_version) = plugin_version.parse::<Version>() else {
+            return UpdateResponse {
+                code: ResponseCode::InvalidRequest,
+                plugin_name: plugin_name.to_owned(),
+                required_files: vec![],
+            };
+        };
+
+        if entry.beta && !allow_beta {
+            return UpdateResponse {
+                code: ResponseCode::NoUpdate,
+                plugin_name: plugin_name.to_owned(),
+                required_files: vec![],
+            };
+        }
+
+        if entry.version <= client_version {
+            return UpdateResponse {
+                code: ResponseCode::NoUpdate,
+                plugin_name: plugin_name.to_owned(),
+                required_files: vec![],
+            };
+        }
+
+        UpdateResponse {
+            code: ResponseCode::Update,
+            plugin_name: plugin_name.to_owned(),
+            required_files: entry.required_files.clone(),
+        }
+    }
+}
+
+fn handle_request_connection(mut stream: TcpStream, catalog: &Catalog) -> eyre::Result<()> {
+    let mut line = String::new();
+    std::io::BufReader::new(&stream).read_line(&mut line)?;
+
+    match serde_json::from_str::<Request>(&line) {
+        Ok(Request::Update { plugin_name, plugin_version, beta, .. }) => {
+            let response = catalog.response_for(&plugin_name, &plugin_version, beta.unwrap_or(false));
+            stream.write_all(serde_json::to_string(&response)?.as_bytes())?;
+        }
+        Ok(Request::UpdateList { plugins }) => {
+            let responses: Vec<UpdateResponse> = plugins.iter()
+                .map(|query| catalog.response_for(&query.plugin_name, &query.plugin_version, query.beta.unwrap_or(false)))
+                .collect();
+            stream.write_all(serde_json::to_string(&responses)?.as_bytes())?;
+        }
+        Ok(Request::Remove { plugin_name, .. }) => {
+            let paths = catalog.plugins.get(&plugin_name)
+                .map(PluginEntry::installed_paths)
+                .unwrap_or_default();
+            stream.write_all(serde_json::to_string(&paths)?.as_bytes())?;
+        }
+        Err(_) => {
+            let response = UpdateResponse {
+                code: ResponseCode::InvalidRequest,
+                plugin_name: String::new(),
+                required_files: vec![],
+            };
+            stream.write_all(serde_json::to_string(&response)?.as_bytes())?;
+        }
+    };
+
+    stream.shutdown(std::net::Shutdown::Both)?;
+
+    Ok(())
+}
+
+fn write_framed(stream: &mut TcpStream, data: &[u8]) -> std::io::Result<()> {
+    stream.write_all(&u64::to_be_bytes(data.len() as u64))?;
+    stream.write_all(data)
+}
+
+fn handle_download_connection(mut stream: TcpStream, catalog: &Catalog) -> eyre::Result<()> {
+    loop {
+        let mut index_buf = [0u8; 8];
+        if stream.read_exact(&mut index_buf).is_err() {
+            // Client closed the connection once it had every file it needed.
+            break;
+        }
+        let index = u64::from_be_bytes(index_buf) as usize;
+
+        let data = match catalog.files.get(index) {
+            Some(data) => data,
+            None => break,
+        };
+
+        write_framed(&mut stream, data)?;
+    }
+
+    Ok(())
+}
+
+fn main() -> eyre::Result<()> {
+    color_eyre::install()?;
+
+    let catalog = Arc::new(Catalog::load()?);
+
+    let request_catalog = catalog.clone();
+    let requests = std::thread::spawn(move || -> eyre::Result<()> {
+        let listener = TcpListener::bind(("0.0.0.0", PORT))?;
+        for stream in listener.incoming() {
+            let catalog = request_catalog.clone();
+            std::thread::spawn(move || {
+                if let Ok(stream) = stream {
+                    if let Err(e) = handle_request_connection(stream, &catalog) {
+                        println!("[server] error handling request: {}", e);
+                    }
+                }
+            });
+        }
+        Ok(())
+    });
+
+    let downloads = std::thread::spawn(move || -> eyre::Result<()> {
+        let listener = TcpListener::bind(("0.0.0.0", PORT + 1))?;
+        for stream in listener.incoming() {
+            let catalog = catalog.clone();
+            std::thread::spawn(move || {
+                if let Ok(stream) = stream {
+                    if let Err(e) = handle_download_connection(stream, &catalog) {
+                        println!("[server] error handling download: {}", e);
+                    }
+                }
+            });
+        }
+        Ok(())
+    });
+
+    let _ = requests.join();
+    let _ = downloads.join();
+
+    Ok(())
+}
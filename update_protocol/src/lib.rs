@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use serde::{Serialize, Deserialize};
+
+/// Where a file (or the root of a directory install) should end up on the
+/// target filesystem.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum InstallLocation {
+    AbsolutePath(String),
+    RelativePath(String),
+}
+
+/// One file the client needs to fetch to apply an update, as carried in
+/// [`UpdateResponse::required_files`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequiredFile {
+    pub install_location: InstallLocation,
+
+    /// Index the client sends back over the download socket to request
+    /// this file's bytes.
+    pub download_index: u64,
+
+    /// sha256 hex digest of the file's contents, so the client can reject a
+    /// corrupted transfer before installing it.
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ResponseCode {
+    NoUpdate,
+    Update,
+    InvalidRequest,
+    PluginNotFound,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateResponse {
+    pub code: ResponseCode,
+    pub plugin_name: String,
+    pub required_files: Vec<RequiredFile>,
+}
+
+/// A single plugin to check in a `Request::UpdateList` batch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginQuery {
+    pub plugin_name: String,
+    pub plugin_version: String,
+    pub beta: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Request {
+    Update {
+        plugin_name: String,
+        plugin_version: String,
+        beta: Option<bool>,
+        options: Option<HashMap<String, String>>,
+    },
+    /// Checks several plugins in one request instead of opening a new
+    /// connection per plugin. The server answers with one `UpdateResponse`
+    /// per entry, in the same order.
+    UpdateList {
+        plugins: Vec<PluginQuery>,
+    },
+    /// Asks for the absolute install paths of the version of `plugin_name`
+    /// the server currently hosts, so the client can remove exactly those
+    /// files. The server answers with a `Vec<String>` of paths;
+    /// `plugin_version` does not change which list is returned.
+    Remove {
+        plugin_name: String,
+        plugin_version: String,
+    },
+}